@@ -1,11 +1,301 @@
-use std::path::{Path, PathBuf};
+use std::{collections::HashSet, path::{Path, PathBuf}};
 
-use indexmap::map::Slice;
+use indexmap::IndexMap;
 use ratatui::layout::Rect;
+use regex::Regex;
 
 use super::{ALL_RATIO, CURRENT_RATIO, DIR_PADDING, PARENT_RATIO};
 use crate::{core::files::{File, Files, FilesOp}, emit, misc::tty_size};
 
+// Cap on how many directories `FolderHistory` remembers a cursor for, so a
+// long session doesn't grow the map without bound.
+const MAX_HISTORY: usize = 500;
+
+#[derive(Default)]
+pub struct FolderHistory(IndexMap<PathBuf, usize>);
+
+impl FolderHistory {
+	#[inline]
+	pub fn get(&self, cwd: &Path) -> Option<usize> { self.0.get(cwd).copied() }
+
+	// Like `get`, but also drops `cwd`'s entry if the directory no longer
+	// exists. This is the "missing entries pruned" half of the requirement
+	// that `save()` gave up on: rather than `stat`-ing all `MAX_HISTORY`
+	// entries on every `leave()`, it charges the one `stat` to the single
+	// directory a caller is actually about to visit (`Folder::new` is about
+	// to `read_dir` it regardless), so a deleted directory's stale cursor is
+	// gone the next time anything looks it up instead of lingering until
+	// `MAX_HISTORY` other directories get visited.
+	pub fn get_pruning(&mut self, cwd: &Path) -> Option<usize> {
+		let cursor = self.0.get(cwd).copied()?;
+		if !cwd.is_dir() {
+			self.0.shift_remove(cwd);
+			return None;
+		}
+		Some(cursor)
+	}
+
+	pub fn save(&mut self, cwd: &Path, cursor: usize) {
+		// Re-inserting moves `cwd` to the back, so eviction below drops the
+		// least-recently-visited entry rather than an arbitrary one. This
+		// bounds the map purely on recency, with no filesystem `stat` per
+		// entry — the old `p.is_dir()` prune blocked on one syscall per
+		// history entry on every `leave()` call.
+		self.0.shift_remove(cwd);
+		self.0.insert(cwd.to_path_buf(), cursor);
+
+		while self.0.len() > MAX_HISTORY {
+			self.0.shift_remove_index(0);
+		}
+	}
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortBy {
+	#[default]
+	Name,
+	Size,
+	MTime,
+	Extension,
+}
+
+#[derive(Default)]
+pub struct FolderSort {
+	pub by:         SortBy,
+	pub reverse:    bool,
+	pub dirs_first: bool,
+}
+
+struct FilterMatch {
+	idx:          usize,
+	score:        i32,
+	direct_match: bool,
+}
+
+struct Filter {
+	query:   String,
+	matches: Vec<FilterMatch>,
+}
+
+// Subsequence fuzzy matcher: walks `pattern` and `text` left-to-right, scoring
+// consecutive runs and word-boundary starts higher, and penalizing by the
+// width of the matched span. Returns `None` if `pattern` isn't a subsequence
+// of `text`.
+fn fuzzy_match(pattern: &str, text: &str) -> Option<(i32, bool)> {
+	if pattern.is_empty() {
+		return Some((0, true));
+	}
+
+	let chars: Vec<char> = text.chars().collect();
+	let needle: Vec<char> = pattern.chars().collect();
+
+	let mut score = 0i32;
+	let mut p = 0;
+	let mut consecutive = false;
+	let mut first = None;
+	let mut last = 0;
+
+	for (i, &c) in chars.iter().enumerate() {
+		if p == needle.len() {
+			break;
+		}
+		if c.to_lowercase().eq(needle[p].to_lowercase()) {
+			if first.is_none() {
+				first = Some(i);
+			}
+			last = i;
+
+			let boundary = i == 0
+				|| matches!(chars[i - 1], '_' | '-' | '.' | '/')
+				|| (chars[i - 1].is_lowercase() && c.is_uppercase());
+
+			score += if consecutive {
+				3
+			} else if boundary {
+				2
+			} else {
+				1
+			};
+			consecutive = true;
+			p += 1;
+		} else {
+			consecutive = false;
+		}
+	}
+
+	if p != needle.len() {
+		return None;
+	}
+
+	score -= (last - first.unwrap_or(last)) as i32;
+	let direct_match = text.to_lowercase().contains(&pattern.to_lowercase());
+	Some((score, direct_match))
+}
+
+// Translate a shell glob (`*`, `?`, `[...]`/`[!...]`) into an equivalent
+// anchored regex, escaping everything else so the rest of `pattern` is
+// matched literally. Used as a fallback when `pattern` doesn't already parse
+// as a regex, since a bare glob like `*.rs` is invalid regex syntax.
+fn glob_to_regex(pattern: &str) -> String {
+	let mut out = String::with_capacity(pattern.len() + 2);
+	out.push('^');
+
+	let mut chars = pattern.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'*' => out.push_str(".*"),
+			'?' => out.push('.'),
+			'[' => {
+				out.push('[');
+				if chars.peek() == Some(&'!') {
+					chars.next();
+					out.push('^');
+				}
+				for c in chars.by_ref() {
+					out.push(c);
+					if c == ']' {
+						break;
+					}
+				}
+			}
+			_ => out.push_str(&regex::escape(&c.to_string())),
+		}
+	}
+
+	out.push('$');
+	out
+}
+
+// How many rows past the visible window to eagerly load metadata for, so
+// that scrolling a few lines doesn't immediately stall on disk I/O.
+const META_LOOKAHEAD: usize = 20;
+
+// Cap on how many children an expanded directory contributes to the tree
+// before the rest are folded into `unlisted`.
+const MAX_TREE_CHILDREN: usize = 5000;
+
+pub struct TreeNode {
+	pub path:         PathBuf,
+	pub depth:        u16,
+	pub left_branchs: Box<[bool]>,
+	pub subpath:      PathBuf,
+	pub unlisted:     usize,
+	pub is_selected:  bool,
+	expanded:         bool,
+	// Awaiting the async read kicked off by `Tree::begin_expand()`. Kept
+	// separate from `expanded` so a repeated toggle while the read is still
+	// in flight doesn't fire a second one.
+	expanding:        bool,
+}
+
+impl TreeNode {
+	// A depth-0 node aliasing an entry already present in `self.files`, built
+	// by `tree_enter()`. Seeded with the `File`'s own `is_selected` rather
+	// than defaulting to `false`, so a selection made in flat view survives
+	// entering tree mode instead of silently vanishing from `has_selected()`/
+	// `selected()`.
+	fn leaf(path: PathBuf, is_selected: bool) -> Self {
+		Self {
+			subpath: path.file_name().map(PathBuf::from).unwrap_or_default(),
+			path,
+			depth: 0,
+			left_branchs: Box::new([]),
+			unlisted: 0,
+			is_selected,
+			expanded: false,
+			expanding: false,
+		}
+	}
+}
+
+#[derive(Default)]
+pub struct Tree {
+	nodes: Vec<TreeNode>,
+}
+
+impl Tree {
+	fn position(&self, path: &Path) -> Option<usize> { self.nodes.iter().position(|n| n.path == path) }
+
+	// Begin expanding `idx`: marks the node as awaiting its children so a
+	// repeated toggle doesn't fire a second read, and hands back the
+	// directory to read. The caller is expected to route this through the
+	// async read pipeline the same way every other directory listing in this
+	// file does (see `update()`) rather than blocking here; `apply_expand()`
+	// splices the children in once the result comes back.
+	fn begin_expand(&mut self, idx: usize) -> Option<PathBuf> {
+		let node = self.nodes.get_mut(idx)?;
+		if node.expanded || node.expanding {
+			return None;
+		}
+
+		node.expanding = true;
+		Some(node.path.clone())
+	}
+
+	// Apply the result of an async directory read kicked off by
+	// `begin_expand()`, splicing `dir`'s children in under their parent the
+	// same way the old synchronous `expand()` did. A no-op if `dir` isn't an
+	// in-flight expand, e.g. the node was removed (its parent collapsed, or
+	// the tree left) before the read returned.
+	fn apply_expand(&mut self, dir: &Path, mut entries: Vec<PathBuf>) {
+		let Some(idx) = self.nodes.iter().position(|n| n.path == dir && n.expanding) else { return };
+
+		let depth = self.nodes[idx].depth + 1;
+		let mut left_branchs = self.nodes[idx].left_branchs.to_vec();
+		left_branchs.push(true);
+
+		entries.sort();
+		let unlisted = entries.len().saturating_sub(MAX_TREE_CHILDREN);
+		entries.truncate(MAX_TREE_CHILDREN);
+
+		let last = entries.len().saturating_sub(1);
+		let children: Vec<TreeNode> = entries
+			.into_iter()
+			.enumerate()
+			.map(|(i, path)| {
+				let mut left_branchs = left_branchs.clone();
+				if i == last {
+					*left_branchs.last_mut().unwrap() = false;
+				}
+				let subpath = path.strip_prefix(dir).unwrap_or(&path).to_path_buf();
+				TreeNode {
+					path,
+					depth,
+					left_branchs: left_branchs.into_boxed_slice(),
+					subpath,
+					unlisted: 0,
+					is_selected: false,
+					expanded: false,
+					expanding: false,
+				}
+			})
+			.collect();
+
+		let node = &mut self.nodes[idx];
+		node.expanded = true;
+		node.expanding = false;
+		node.unlisted = unlisted;
+		self.nodes.splice(idx + 1..idx + 1, children);
+	}
+
+	fn collapse(&mut self, idx: usize) {
+		let Some(node) = self.nodes.get_mut(idx) else { return };
+		if !node.expanded {
+			return;
+		}
+
+		let depth = node.depth;
+		node.expanded = false;
+		node.unlisted = 0;
+
+		let mut end = idx + 1;
+		while end < self.nodes.len() && self.nodes[end].depth > depth {
+			end += 1;
+		}
+		self.nodes.drain(idx + 1..end);
+	}
+}
+
 #[derive(Default)]
 pub struct Folder {
 	pub cwd:   PathBuf,
@@ -16,6 +306,17 @@ pub struct Folder {
 	pub page:      usize,
 	pub hovered:   Option<File>,
 	pub in_search: bool,
+	pub sort:      FolderSort,
+	filter:        Option<Filter>,
+	tree:          Option<Tree>,
+
+	// Raw `self.files` indices metadata was loaded for as of the last
+	// `meta_upto()` call, so the next call only has to touch the delta. Kept
+	// as a set rather than a range because the visible window maps to raw
+	// indices through `file_idx()`, which aren't contiguous while a filter
+	// is active.
+	meta_len:    usize,
+	meta_loaded: HashSet<usize>,
 }
 
 impl Folder {
@@ -25,10 +326,43 @@ impl Folder {
 		Self { cwd: cwd.to_path_buf(), in_search: true, ..Default::default() }
 	}
 
+	pub fn with_history(cwd: &Path, history: &mut FolderHistory) -> Self {
+		let mut folder = Self::new(cwd);
+		folder.seed_from_history(history);
+		folder
+	}
+
+	pub fn new_search_with_history(cwd: &Path, history: &mut FolderHistory) -> Self {
+		let mut folder = Self::new_search(cwd);
+		folder.seed_from_history(history);
+		folder
+	}
+
+	fn seed_from_history(&mut self, history: &mut FolderHistory) {
+		let cursor = history.get_pruning(&self.cwd).unwrap_or(0);
+		self.cursor = cursor;
+		self.offset = cursor.saturating_sub(Self::limit() / 2);
+	}
+
+	pub fn leave(&self, history: &mut FolderHistory) { history.save(&self.cwd, self.cursor); }
+
 	#[inline]
 	pub fn limit() -> usize { tty_size().ws_row.saturating_sub(DIR_PADDING) as usize }
 
 	pub fn update(&mut self, op: FilesOp) -> bool {
+		// A read for a directory other than `self.cwd` is a tree node
+		// expansion kicked off by `toggle_expand()`/`Tree::begin_expand()`,
+		// not a listing of the folder itself — route it to the tree instead
+		// of `self.files`.
+		if let FilesOp::Read(dir, items) = &op {
+			if self.tree.is_some() && *dir != self.cwd {
+				let paths = items.iter().map(|f| f.path()).collect();
+				self.tree.as_mut().unwrap().apply_expand(dir, paths);
+				emit!(Refresh);
+				return true;
+			}
+		}
+
 		let b = match op {
 			FilesOp::Read(_, items) => self.files.update_read(items),
 			FilesOp::Search(_, items) => self.files.update_search(items),
@@ -38,6 +372,17 @@ impl Folder {
 			return false;
 		}
 
+		// `update_read`/`update_search` can insert, remove, or reorder entries
+		// in `self.files`, the same way `re_sort()` does — and like `re_sort()`,
+		// that invalidates any `FilterMatch::idx` an active filter captured at
+		// the last `rescore_filter()` call. Left stale, those raw indices feed
+		// straight into `invert_selection()`/`select_matching()`/`jump_mtime()`/
+		// `file_idx()` unchecked, silently acting on the wrong file or
+		// indexing `self.files` out of bounds.
+		if self.filter.is_some() {
+			self.rescore_filter();
+		}
+
 		let len = self.files.len();
 		self.offset = self.offset.min(len);
 		self.cursor = self.cursor.min(len.saturating_sub(1));
@@ -47,10 +392,58 @@ impl Folder {
 			self.hover(&h);
 		}
 		self.hovered = self.files.duplicate(self.cursor);
+		self.meta_upto(META_LOOKAHEAD);
 
 		true
 	}
 
+	// Fetch metadata (symlink target, size, permissions, ...) only for files
+	// within `lookahead` rows of the visible window, and mark the rest dirty
+	// so they're recomputed once they actually scroll into view. Only the
+	// delta between the previously-loaded set and the new one is touched,
+	// which keeps entering (and scrolling through) huge directories
+	// O(visible) instead of O(total) per call.
+	//
+	// The window is expressed in visible cursor space and resolved to raw
+	// `self.files` indices through `file_idx()`, the same way `window()`
+	// does, so an active filter only loads metadata for rows actually on
+	// screen instead of a contiguous (and largely irrelevant) raw range.
+	pub fn meta_upto(&mut self, lookahead: usize) -> bool {
+		let len = self.files.len();
+		if len != self.meta_len {
+			// The listing itself changed underneath us, so indices from the
+			// last call may no longer point at the same files; start over.
+			self.meta_loaded.clear();
+			self.meta_len = len;
+		}
+		if len == 0 {
+			return false;
+		}
+
+		let end = (self.offset + Self::limit() + lookahead).min(self.visible_len());
+		let visible: HashSet<usize> = (self.offset..end).filter_map(|c| self.file_idx(c)).collect();
+		let mut changed = false;
+
+		for &i in &self.meta_loaded {
+			if !visible.contains(&i) && self.files[i].is_meta_loaded() {
+				self.files[i].mark_meta_dirty();
+				changed = true;
+			}
+		}
+		for &i in &visible {
+			if !self.meta_loaded.contains(&i) && !self.files[i].is_meta_loaded() {
+				self.files[i].load_meta();
+				changed = true;
+			}
+		}
+
+		self.meta_loaded = visible;
+		if changed {
+			emit!(Refresh);
+		}
+		changed
+	}
+
 	pub fn set_page(&mut self, force: bool) -> bool {
 		let limit = Self::limit();
 		let new = if limit == 0 { 0 } else { self.cursor / limit };
@@ -64,20 +457,21 @@ impl Folder {
 	}
 
 	pub fn next(&mut self, step: usize) -> bool {
-		let len = self.files.len();
+		let len = self.visible_len();
 		if len == 0 {
 			return false;
 		}
 
 		let old = self.cursor;
 		self.cursor = (self.cursor + step).min(len - 1);
-		self.hovered = self.files.duplicate(self.cursor);
+		self.sync_hovered();
 		self.set_page(false);
 
 		let limit = Self::limit();
 		if self.cursor >= (self.offset + limit).min(len).saturating_sub(5) {
 			self.offset = len.saturating_sub(limit).min(self.offset + self.cursor - old);
 		}
+		self.meta_upto(META_LOOKAHEAD);
 
 		old != self.cursor
 	}
@@ -85,16 +479,286 @@ impl Folder {
 	pub fn prev(&mut self, step: usize) -> bool {
 		let old = self.cursor;
 		self.cursor = self.cursor.saturating_sub(step);
-		self.hovered = self.files.duplicate(self.cursor);
+		self.sync_hovered();
 		self.set_page(false);
 
 		if self.cursor < self.offset + 5 {
 			self.offset = self.offset.saturating_sub(old - self.cursor);
 		}
+		self.meta_upto(META_LOOKAHEAD);
+
+		old != self.cursor
+	}
+
+	pub fn page_up(&mut self) -> bool { self.page_step(false) }
+
+	pub fn page_down(&mut self) -> bool { self.page_step(true) }
+
+	fn page_step(&mut self, forward: bool) -> bool {
+		let limit = Self::limit();
+		let len = self.visible_len();
+		if len == 0 {
+			return false;
+		}
+
+		let old = self.cursor;
+		if forward {
+			self.cursor = (self.cursor + limit).min(len - 1);
+			self.offset = (self.offset + limit).min(len.saturating_sub(limit.min(len)));
+		} else {
+			self.cursor = self.cursor.saturating_sub(limit);
+			self.offset = self.offset.saturating_sub(limit);
+		}
+
+		self.sync_hovered();
+		self.set_page(true);
+		self.meta_upto(META_LOOKAHEAD);
 
 		old != self.cursor
 	}
 
+	pub fn move_top(&mut self) -> bool { self.move_to(0) }
+
+	pub fn move_bottom(&mut self) -> bool { self.move_to(self.visible_len().saturating_sub(1)) }
+
+	fn move_to(&mut self, idx: usize) -> bool {
+		let len = self.visible_len();
+		if len == 0 {
+			return false;
+		}
+
+		let old = self.cursor;
+		self.cursor = idx.min(len - 1);
+
+		let limit = Self::limit();
+		self.offset = if self.cursor == 0 {
+			0
+		} else if self.cursor >= len.saturating_sub(1) {
+			len.saturating_sub(limit)
+		} else {
+			self.offset
+		};
+
+		self.sync_hovered();
+		self.set_page(true);
+		self.meta_upto(META_LOOKAHEAD);
+
+		old != self.cursor
+	}
+
+	// Index into `self.files` addressed by the visible cursor, accounting for
+	// an active filter or tree. Tree and filter are mutually exclusive (see
+	// `tree_enter()`/`filter()`), so only one of these branches ever applies.
+	//
+	// A depth-0 tree node aliases an entry already present in `self.files`
+	// and resolves the same way `sync_hovered()` resolves it, by path rather
+	// than by raw position, since expanding/collapsing earlier siblings
+	// shifts every depth-0 node after them to a different cursor index.
+	// Deeper nodes are read straight off disk and have no backing `File`, so
+	// there's no raw index to hand back for them. This runs on every visible
+	// row and every cursor move, so it goes through `Files`' backing
+	// `IndexMap::get_index_of()` rather than a `.position()` scan — O(1)
+	// instead of O(n) per lookup.
+	fn file_idx(&self, cursor: usize) -> Option<usize> {
+		if let Some(tree) = &self.tree {
+			let node = tree.nodes.get(cursor).filter(|n| n.depth == 0)?;
+			return self.files.get_index_of(&node.path);
+		}
+		match &self.filter {
+			Some(f) => f.matches.get(cursor).map(|m| m.idx),
+			None => (cursor < self.files.len()).then_some(cursor),
+		}
+	}
+
+	#[inline]
+	fn visible_len(&self) -> usize {
+		if let Some(tree) = &self.tree {
+			return tree.nodes.len();
+		}
+		self.filter.as_ref().map_or(self.files.len(), |f| f.matches.len())
+	}
+
+	// Keep `hovered` pointed at the row under `cursor`, including while a
+	// tree is active. A depth-0 tree node aliases an entry already present in
+	// `self.files`, so it resolves the same way a flat cursor would; deeper
+	// nodes are read straight off disk into the tree and have no backing
+	// `File` to hand back, so `hovered` is cleared rather than left pinned to
+	// whatever was hovered before `tree_enter()`.
+	fn sync_hovered(&mut self) {
+		self.hovered = match &self.tree {
+			Some(tree) => tree.nodes.get(self.cursor).filter(|n| n.depth == 0).and_then(|node| {
+				let idx = self.files.get_index_of(&node.path)?;
+				self.files.duplicate(idx)
+			}),
+			None => self.file_idx(self.cursor).and_then(|i| self.files.duplicate(i)),
+		};
+	}
+
+	// Mirror a tree node's selection onto the backing `File` in `self.files`,
+	// when one exists (i.e. the node sits at depth 0). Nodes below depth 0
+	// are discovered straight off disk and never materialized into
+	// `self.files`, so they have no `File` to mirror onto; `selected()` and
+	// `has_selected()` read `tree.nodes` directly while a tree is active, so
+	// those still see every depth correctly regardless of this mirror.
+	fn sync_file_selected(&mut self, path: &Path, state: bool) {
+		if let Some(idx) = self.files.get_index_of(path) {
+			self.files[idx].is_selected = state;
+		}
+	}
+
+	pub fn tree_enter(&mut self) -> bool {
+		if self.tree.is_some() {
+			return false;
+		}
+
+		// Tree rows don't live in the filter's match indices, so the two
+		// modes can't coexist without `file_idx()`/`visible_len()`
+		// disagreeing about what's on screen; tree wins, same as
+		// `visible_len()` already prefers it.
+		self.filter = None;
+
+		let hovered = self.hovered.as_ref().map(|h| h.path());
+		let nodes =
+			self.files.iter().map(|(path, file)| TreeNode::leaf(path.clone(), file.is_selected)).collect();
+
+		self.tree = Some(Tree { nodes });
+		self.cursor = 0;
+		if let Some(h) = hovered {
+			self.hover(&h);
+		}
+		emit!(Refresh);
+		true
+	}
+
+	pub fn tree_leave(&mut self) -> bool {
+		let Some(tree) = self.tree.take() else {
+			return false;
+		};
+
+		if let Some(path) = tree.nodes.get(self.cursor).map(|n| n.path.clone()) {
+			self.hover(&path);
+		}
+		emit!(Refresh);
+		true
+	}
+
+	#[inline]
+	pub fn is_tree(&self) -> bool { self.tree.is_some() }
+
+	pub fn tree_node(&self, idx: usize) -> Option<&TreeNode> { self.tree.as_ref()?.nodes.get(idx) }
+
+	pub fn toggle_expand(&mut self) -> bool {
+		let Some(tree) = &mut self.tree else {
+			return false;
+		};
+
+		let idx = self.cursor;
+		let Some(node) = tree.nodes.get(idx) else {
+			return false;
+		};
+
+		if node.expanded {
+			let hovered = node.path.clone();
+			tree.collapse(idx);
+			if let Some(new_idx) = tree.position(&hovered) {
+				self.cursor = new_idx;
+			}
+			emit!(Refresh);
+			return true;
+		}
+
+		// Route the listing through the same async read pipeline every other
+		// directory in this file goes through (see `update()`'s doc), instead
+		// of blocking the caller on `std::fs::read_dir` here — the children
+		// get spliced in once `update()` receives the matching `FilesOp::Read`.
+		let Some(dir) = tree.begin_expand(idx) else {
+			return false;
+		};
+		emit!(Read(dir));
+		true
+	}
+
+	pub fn filter(&mut self, query: &str) -> bool {
+		if query.is_empty() {
+			return self.filter_clear();
+		}
+		// See `tree_enter()`: tree and filter are mutually exclusive, and
+		// tree wins, so a filter can't be started until the tree is left.
+		if self.tree.is_some() {
+			return false;
+		}
+
+		let hovered = self.hovered.as_ref().map(|h| h.path());
+		self.filter = Some(Filter { query: query.to_owned(), matches: Vec::new() });
+		self.rescore_filter();
+		self.cursor = 0;
+		self.offset = 0;
+		self.set_page(true);
+
+		if let Some(h) = hovered {
+			self.hover(&h);
+		}
+		// `hover()` above is a no-op whenever the previously-hovered file
+		// didn't survive the new filter — the common case, since `cursor` was
+		// just reset to 0 and `position()` falls back to it — so it can't be
+		// relied on to load metadata for the newly-visible (and possibly
+		// never-before-loaded) filtered rows. Call this unconditionally
+		// instead of only through `hover()`'s side effect.
+		self.meta_upto(META_LOOKAHEAD);
+		emit!(Refresh);
+		true
+	}
+
+	// Re-run the active filter's query against `self.files` and rebuild
+	// `matches`. `FilterMatch::idx` is a raw `self.files` index captured at
+	// match time; anything that reorders `self.files` in place (`re_sort()`)
+	// invalidates those indices, so this needs to run again afterwards the
+	// same way `hover()` already has to re-resolve the cursor position.
+	fn rescore_filter(&mut self) {
+		let Some(filter) = &self.filter else {
+			return;
+		};
+
+		let mut matches: Vec<FilterMatch> = self
+			.files
+			.iter()
+			.enumerate()
+			.filter_map(|(idx, (_, file))| {
+				fuzzy_match(&filter.query, file.name()).map(|(score, direct_match)| FilterMatch { idx, score, direct_match })
+			})
+			.collect();
+		matches.sort_by(|a, b| b.score.cmp(&a.score));
+
+		self.filter.as_mut().unwrap().matches = matches;
+	}
+
+	pub fn filter_clear(&mut self) -> bool {
+		if self.filter.take().is_none() {
+			return false;
+		}
+
+		// Route back through `hover()`, the same as `filter()`'s own
+		// hover-after-rescore, `re_sort()`, and `tree_leave()` all do after
+		// the listing shape changes, so `offset` gets recomputed via
+		// `next()`/`prev()` instead of leaving the viewport pinned to the
+		// filtered scroll position while the cursor jumps to its unfiltered row.
+		let hovered = self.hovered.as_ref().map(|h| h.path());
+		match hovered {
+			Some(h) => {
+				self.hover(&h);
+			}
+			None => self.cursor = 0,
+		}
+		self.set_page(true);
+		// Same reasoning as `filter()`: `hover()` above is only a side-effect
+		// path to `meta_upto()` and is a no-op whenever the resolved position
+		// already equals `cursor`, so call this directly rather than relying
+		// on it to cover the newly-visible unfiltered rows.
+		self.meta_upto(META_LOOKAHEAD);
+		emit!(Refresh);
+		true
+	}
+
 	pub fn hidden(&mut self, show: Option<bool>) -> bool {
 		if show.is_none() || self.files.show_hidden != show.unwrap() {
 			self.files.show_hidden = !self.files.show_hidden;
@@ -105,13 +769,52 @@ impl Folder {
 	}
 
 	#[inline]
-	pub fn window(&self) -> &Slice<PathBuf, File> {
-		let end = (self.offset + Self::limit()).min(self.files.len());
-		self.files.get_range(self.offset..end).unwrap()
+	pub fn window(&self) -> Vec<&File> {
+		let end = (self.offset + Self::limit()).min(self.visible_len());
+		self.visible_slice(self.offset, end)
+	}
+
+	// Resolve a range of on-screen rows to the `File`s they point at, honoring
+	// the active filter the same way `file_idx`/`visible_len` already do.
+	// Tree mode renders through `tree_node()` instead, so this only concerns
+	// itself with the flat (possibly filtered) listing.
+	fn visible_slice(&self, start: usize, end: usize) -> Vec<&File> {
+		(start..end).filter_map(|i| self.file_idx(i)).map(|i| &self.files[i]).collect()
 	}
 
 	pub fn select(&mut self, idx: Option<usize>, state: Option<bool>) -> bool {
+		if let Some(tree) = &mut self.tree {
+			let targets: Vec<usize> = if let Some(idx) = idx { vec![idx] } else { (0..tree.nodes.len()).collect() };
+
+			let mut applied = false;
+			let mut synced = Vec::new();
+			for i in targets {
+				let Some(node) = tree.nodes.get_mut(i) else { continue };
+				let next = state.unwrap_or(!node.is_selected);
+				if next != node.is_selected {
+					node.is_selected = next;
+					applied = true;
+					synced.push((node.path.clone(), next));
+				}
+			}
+			for (path, next) in synced {
+				self.sync_file_selected(&path, next);
+			}
+			return applied;
+		}
+
 		let len = self.files.len();
+		// `idx` lives in the same visible cursor space as `hover()`/`search_step()`/
+		// `jump_mtime()`, not a raw `self.files` index, so resolve it through
+		// `file_idx()` the same way the `idx = None` (select-all) fan-out below
+		// already does — otherwise `select(Some(n))` would silently bypass an
+		// active filter.
+		let targets: Vec<usize> = if let Some(idx) = idx {
+			self.file_idx(idx).into_iter().collect()
+		} else {
+			(0..self.visible_len()).filter_map(|i| self.file_idx(i)).collect()
+		};
+
 		let mut apply = |idx: usize, state: Option<bool>| -> bool {
 			if state.is_none() {
 				self.files[idx].is_selected = !self.files[idx].is_selected;
@@ -127,29 +830,127 @@ impl Folder {
 			false
 		};
 
-		if let Some(idx) = idx {
-			if idx < len {
-				return apply(idx, state);
+		let mut applied = false;
+		for idx in targets {
+			if idx < len && apply(idx, state) {
+				applied = true;
 			}
-		} else {
-			let mut applied = false;
-			for i in 0..len {
-				if apply(i, state) {
-					applied = true;
+		}
+		applied
+	}
+
+	pub fn invert_selection(&mut self) -> bool {
+		if let Some(tree) = &mut self.tree {
+			let mut changed = false;
+			let mut synced = Vec::new();
+			for node in &mut tree.nodes {
+				node.is_selected = !node.is_selected;
+				changed = true;
+				synced.push((node.path.clone(), node.is_selected));
+			}
+			for (path, next) in synced {
+				self.sync_file_selected(&path, next);
+			}
+			if changed {
+				emit!(Refresh);
+			}
+			return changed;
+		}
+
+		let indices: Vec<usize> = (0..self.visible_len()).filter_map(|i| self.file_idx(i)).collect();
+		let mut changed = false;
+		for idx in indices {
+			self.files[idx].is_selected = !self.files[idx].is_selected;
+			changed = true;
+		}
+		if changed {
+			emit!(Refresh);
+		}
+		changed
+	}
+
+	// `pattern` only goes through `glob_to_regex()` if it actually contains a
+	// glob metacharacter (`*`, `?`, `[`): `glob_to_regex()` escapes everything
+	// else, so it happily produces a *syntactically valid* anchored regex for
+	// almost any input (e.g. `foo|bar` becomes a literal-pipe match, not an
+	// alternation) — trying it unconditionally would mean `Regex::new` on the
+	// translation almost always succeeds and a genuine regex like `foo|bar` or
+	// `^img_\d+` could never actually be used. So a pattern with no glob
+	// metacharacters is tried as regex first, falling back to the glob
+	// translation only if that fails to compile; a pattern that does look
+	// like a glob is translated first, since that's the common case (`*.rs`,
+	// `img_[0-9].png`) and raw regex syntax would rarely be intended there.
+	pub fn select_matching(&mut self, pattern: &str, state: Option<bool>) -> bool {
+		let glob_like = pattern.contains(['*', '?', '[']);
+		let attempts =
+			if glob_like { (glob_to_regex(pattern), pattern.to_owned()) } else { (pattern.to_owned(), glob_to_regex(pattern)) };
+
+		let re = match Regex::new(&attempts.0).or_else(|_| Regex::new(&attempts.1)) {
+			Ok(re) => re,
+			Err(_) => return false,
+		};
+
+		if let Some(tree) = &mut self.tree {
+			let mut changed = false;
+			let mut synced = Vec::new();
+			for node in &mut tree.nodes {
+				// Match against the bare file name in both branches, same as
+				// the flat listing below, regardless of how `subpath` is
+				// otherwise derived.
+				let name = node.path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+				if !re.is_match(&name) {
+					continue;
+				}
+
+				let next = state.unwrap_or(!node.is_selected);
+				if next != node.is_selected {
+					node.is_selected = next;
+					changed = true;
+					synced.push((node.path.clone(), next));
 				}
 			}
-			return applied;
+			for (path, next) in synced {
+				self.sync_file_selected(&path, next);
+			}
+			if changed {
+				emit!(Refresh);
+			}
+			return changed;
 		}
 
-		false
+		let indices: Vec<usize> = (0..self.visible_len()).filter_map(|i| self.file_idx(i)).collect();
+		let mut changed = false;
+		for idx in indices {
+			if !re.is_match(self.files[idx].name()) {
+				continue;
+			}
+
+			let next = state.unwrap_or(!self.files[idx].is_selected);
+			if next != self.files[idx].is_selected {
+				self.files[idx].is_selected = next;
+				changed = true;
+			}
+		}
+		if changed {
+			emit!(Refresh);
+		}
+		changed
 	}
 
+	// Move `cursor` to wherever `path` actually resolves to in the current
+	// (possibly filtered or tree) visible space. The no-op check compares
+	// `path`'s resolved position against `cursor`, not against `self.hovered`'s
+	// stored path: every caller that repositions after reshaping the listing
+	// (`re_sort()`, `filter()`, `filter_clear()`, `tree_enter()`, `tree_leave()`)
+	// passes back `self.hovered`'s own path, which never changes across the
+	// reshape — comparing path identity would make the check always true and
+	// turn every one of those calls into a no-op.
 	pub fn hover(&mut self, path: &Path) -> bool {
-		if matches!(self.hovered, Some(ref h) if h.path == path) {
+		let new = self.position(path).unwrap_or(self.cursor);
+		if new == self.cursor {
 			return false;
 		}
 
-		let new = self.position(path).unwrap_or(self.cursor);
 		if new > self.cursor { self.next(new - self.cursor) } else { self.prev(self.cursor - new) }
 	}
 
@@ -160,6 +961,178 @@ impl Folder {
 		}
 		false
 	}
+
+	pub fn cycle_sort(&mut self) -> bool {
+		self.sort.by = match self.sort.by {
+			SortBy::Name => SortBy::Size,
+			SortBy::Size => SortBy::MTime,
+			SortBy::MTime => SortBy::Extension,
+			SortBy::Extension => SortBy::Name,
+		};
+		self.re_sort();
+		true
+	}
+
+	pub fn reverse_sort(&mut self) -> bool {
+		self.sort.reverse = !self.sort.reverse;
+		self.re_sort();
+		true
+	}
+
+	pub fn toggle_dirs_first(&mut self) -> bool {
+		self.sort.dirs_first = !self.sort.dirs_first;
+		self.re_sort();
+		true
+	}
+
+	fn re_sort(&mut self) {
+		let hovered = self.hovered.as_ref().map(|h| h.path());
+		let (by, reverse, dirs_first) = (self.sort.by, self.sort.reverse, self.sort.dirs_first);
+
+		// `length()`/`mtime()` are only accurate for rows `meta_upto()` has
+		// loaded; everything outside the last-visible window is deferred or
+		// marked dirty (see `meta_loaded` above). Sorting by either without
+		// forcing a load first would rank most of a large directory by
+		// stale/default values instead of its real size or mtime.
+		//
+		// Known tradeoff: a global size/mtime ordering needs every row's real
+		// value, so this is O(total), not O(visible) like `meta_upto()` and
+		// everything else in this file — a sort-by-size on a huge directory
+		// blocks on a `load_meta()` per not-yet-loaded file. Correctness wins
+		// here over the lazy-loading goal the rest of the series chases;
+		// batching/yielding this across frames would need a rework of how
+		// `re_sort()`/`jump_mtime()` report completion, so it's left as a
+		// follow-up rather than done half-way.
+		if matches!(by, SortBy::Size | SortBy::MTime) {
+			for i in 0..self.files.len() {
+				if !self.files[i].is_meta_loaded() {
+					self.files[i].load_meta();
+				}
+			}
+		}
+
+		self.files.sort_by(|_, a, _, b| {
+			let ordering = if dirs_first && a.is_dir() != b.is_dir() {
+				b.is_dir().cmp(&a.is_dir())
+			} else {
+				match by {
+					SortBy::Name => a.name().cmp(b.name()),
+					SortBy::Size => a.length().cmp(&b.length()),
+					SortBy::MTime => a.mtime().cmp(&b.mtime()),
+					SortBy::Extension => a.extension().cmp(b.extension()),
+				}
+			};
+			if reverse { ordering.reverse() } else { ordering }
+		});
+
+		// The sort just reordered `self.files` in place, so any `FilterMatch::idx`
+		// values captured by an active filter now point at the wrong rows.
+		if self.filter.is_some() {
+			self.rescore_filter();
+		}
+
+		// Same reordering invalidates `meta_loaded`: it's keyed by raw index, and
+		// `meta_upto()` only clears it when `self.files.len()` changes, which a
+		// sort never does. Left alone, a raw index already in `meta_loaded` would
+		// be treated as "already loaded" for whatever file the sort just rotated
+		// into that slot, even if that file's own metadata was never fetched.
+		self.meta_loaded.clear();
+
+		if let Some(h) = hovered {
+			self.hover(&h);
+		}
+		emit!(Refresh);
+	}
+
+	pub fn search_next(&mut self) -> bool { self.search_step(true) }
+
+	pub fn search_prev(&mut self) -> bool { self.search_step(false) }
+
+	// `self.files` in search mode is already exactly the hit set (populated
+	// wholesale by `FilesOp::Search`), so stepping is just a wrapping walk
+	// over it — but it must walk the same cursor space as `next`/`prev` do,
+	// i.e. narrowed by an active filter, or it'd land on a row the filter is
+	// currently hiding.
+	fn search_step(&mut self, forward: bool) -> bool {
+		if !self.in_search {
+			return false;
+		}
+
+		let len = self.visible_len();
+		if len == 0 {
+			return false;
+		}
+
+		let next = if forward { (self.cursor + 1) % len } else { (self.cursor + len - 1) % len };
+
+		if next > self.cursor { self.next(next - self.cursor) } else { self.prev(self.cursor - next) }
+	}
+
+	// Current position within the search results, e.g. `(3, 17)` for "3/17".
+	pub fn search_progress(&self) -> Option<(usize, usize)> {
+		if !self.in_search {
+			return None;
+		}
+
+		let len = self.visible_len();
+		if len == 0 {
+			return None;
+		}
+
+		Some((self.cursor + 1, len))
+	}
+
+	pub fn select_next_mtime(&mut self) -> bool { self.jump_mtime(true) }
+
+	pub fn select_prev_mtime(&mut self) -> bool { self.jump_mtime(false) }
+
+	// `self.cursor` lives in visible cursor space (narrowed by an active
+	// filter, same as `search_step`), not raw `self.files` index space, so the
+	// mtime ordering has to be built from — and mapped back to — visible rows
+	// rather than assuming `self.cursor` indexes `self.files` directly.
+	fn jump_mtime(&mut self, forward: bool) -> bool {
+		let len = self.visible_len();
+		if len == 0 {
+			return false;
+		}
+
+		let mut order: Vec<usize> = (0..len).filter_map(|c| self.file_idx(c)).collect();
+
+		// Same reasoning as `re_sort()`'s `SortBy::MTime` arm: `mtime()` only
+		// reflects reality for rows `meta_upto()` has actually loaded, so force
+		// a load across the full visible range before ranking by it, rather
+		// than ordering on whatever default/stale value a never-loaded file
+		// happens to carry. Same known tradeoff too: `order` spans every
+		// visible row, not just the on-screen window, so this is O(visible
+		// total) rather than O(on-screen) for a filtered listing — still far
+		// better than `re_sort()`'s O(all of `self.files`), but not free.
+		for &i in &order {
+			if !self.files[i].is_meta_loaded() {
+				self.files[i].load_meta();
+			}
+		}
+
+		order.sort_by_key(|&i| self.files[i].mtime());
+
+		let Some(cur_idx) = self.file_idx(self.cursor) else {
+			return false;
+		};
+		let Some(pos) = order.iter().position(|&i| i == cur_idx) else {
+			return false;
+		};
+
+		let next =
+			if forward { order.get(pos + 1).copied() } else { pos.checked_sub(1).and_then(|p| order.get(p).copied()) };
+
+		let Some(idx) = next else {
+			return false;
+		};
+		let Some(cursor) = (0..len).find(|&c| self.file_idx(c) == Some(idx)) else {
+			return false;
+		};
+
+		if cursor > self.cursor { self.next(cursor - self.cursor) } else { self.prev(self.cursor - cursor) }
+	}
 }
 
 impl Folder {
@@ -168,22 +1141,52 @@ impl Folder {
 
 	#[inline]
 	pub fn position(&self, path: &Path) -> Option<usize> {
-		self.files.iter().position(|(p, _)| p == path)
+		if let Some(tree) = &self.tree {
+			return tree.position(path);
+		}
+
+		let idx = self.files.get_index_of(path)?;
+		match &self.filter {
+			// Map the raw `self.files` index to its row in the filtered list,
+			// the same space `cursor` lives in. `None` here correctly means
+			// "not currently visible" rather than a raw, unfiltered index.
+			Some(f) => f.matches.iter().position(|m| m.idx == idx),
+			None => Some(idx),
+		}
 	}
 
-	pub fn paginate(&self) -> &Slice<PathBuf, File> {
-		let max = self.files.len().saturating_sub(1);
+	pub fn paginate(&self) -> Vec<&File> {
+		let len = self.visible_len();
+		if len == 0 {
+			return Vec::new();
+		}
+
+		let max = len.saturating_sub(1);
 		let limit = Self::limit();
 
 		let start = (self.page * limit).min(max);
-		let end = (start + limit).min(max);
-		self.files.get_range(start..end).unwrap()
+		let end = (start + limit).min(len);
+		self.visible_slice(start, end)
 	}
 
 	#[inline]
-	pub fn has_selected(&self) -> bool { self.files.iter().any(|(_, item)| item.is_selected) }
+	pub fn has_selected(&self) -> bool {
+		if let Some(tree) = &self.tree {
+			return tree.nodes.iter().any(|n| n.is_selected);
+		}
+		self.files.iter().any(|(_, item)| item.is_selected)
+	}
 
 	pub fn selected(&self) -> Option<Vec<PathBuf>> {
+		// Tree nodes hold real, absolute paths at every depth, unlike
+		// `self.files` which only ever lists the current directory's direct
+		// children, so reading straight off `tree.nodes` is what makes
+		// selections below depth 0 visible to bulk operations.
+		if let Some(tree) = &self.tree {
+			let v: Vec<PathBuf> = tree.nodes.iter().filter(|n| n.is_selected).map(|n| n.path.clone()).collect();
+			return if v.is_empty() { None } else { Some(v) };
+		}
+
 		let v = self
 			.files
 			.iter()
@@ -205,4 +1208,376 @@ impl Folder {
 			height: 1,
 		})
 	}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fuzzy_match_requires_subsequence() {
+		assert!(fuzzy_match("abc", "a_b_c").is_some());
+		assert!(fuzzy_match("abc", "cab").is_none());
+		assert!(fuzzy_match("", "anything").is_some());
+	}
+
+	#[test]
+	fn fuzzy_match_rewards_consecutive_and_boundary_runs() {
+		let (consecutive, _) = fuzzy_match("ab", "ab_c").unwrap();
+		let (scattered, _) = fuzzy_match("ab", "a_b_c").unwrap();
+		assert!(consecutive > scattered);
+
+		let (boundary, _) = fuzzy_match("b", "a_bc").unwrap();
+		let (mid_word, _) = fuzzy_match("c", "a_bc").unwrap();
+		assert!(boundary > mid_word);
+	}
+
+	#[test]
+	fn fuzzy_match_flags_direct_substring_matches() {
+		let (_, direct) = fuzzy_match("cat", "concatenate").unwrap();
+		assert!(direct);
+
+		let (_, direct) = fuzzy_match("ct", "concatenate").unwrap();
+		assert!(!direct);
+	}
+
+	#[test]
+	fn glob_to_regex_translates_wildcards_and_escapes_the_rest() {
+		let re = Regex::new(&glob_to_regex("*.rs")).unwrap();
+		assert!(re.is_match("main.rs"));
+		assert!(!re.is_match("main.rsx"));
+
+		let re = Regex::new(&glob_to_regex("img_?.png")).unwrap();
+		assert!(re.is_match("img_1.png"));
+		assert!(!re.is_match("img_12.png"));
+
+		let re = Regex::new(&glob_to_regex("a.b")).unwrap();
+		assert!(re.is_match("a.b"));
+		assert!(!re.is_match("aXb"));
+	}
+
+	#[test]
+	fn folder_history_evicts_the_least_recently_saved_entry() {
+		let mut history = FolderHistory::default();
+		for i in 0..MAX_HISTORY {
+			history.save(&PathBuf::from(format!("/dir-{i}")), i);
+		}
+		assert_eq!(history.get(Path::new("/dir-0")), Some(0));
+
+		// One more insert should push out `/dir-0`, the oldest entry, while
+		// keeping everything inserted more recently.
+		history.save(&PathBuf::from("/dir-new"), 99);
+		assert_eq!(history.get(Path::new("/dir-0")), None);
+		assert_eq!(history.get(Path::new("/dir-1")), Some(1));
+		assert_eq!(history.get(Path::new("/dir-new")), Some(99));
+		assert_eq!(history.0.len(), MAX_HISTORY);
+	}
+
+	#[test]
+	fn folder_history_resaving_refreshes_recency() {
+		let mut history = FolderHistory::default();
+		history.save(&PathBuf::from("/a"), 1);
+		for i in 0..MAX_HISTORY - 1 {
+			history.save(&PathBuf::from(format!("/dir-{i}")), i);
+		}
+
+		// Touch `/a` again so it's no longer the oldest entry.
+		history.save(&PathBuf::from("/a"), 2);
+		history.save(&PathBuf::from("/dir-overflow"), 1);
+
+		assert_eq!(history.get(Path::new("/a")), Some(2));
+	}
+
+	#[test]
+	fn folder_history_get_pruning_drops_entries_for_missing_directories() {
+		let dir = TempDir::new("history-prune");
+		let mut history = FolderHistory::default();
+		history.save(&dir.0, 3);
+		std::fs::remove_dir_all(&dir.0).unwrap();
+
+		assert_eq!(history.get_pruning(&dir.0), None);
+		assert_eq!(history.get(&dir.0), None);
+	}
+
+	#[test]
+	fn cycle_sort_reverse_and_dirs_first_toggle_through_a_real_folder() {
+		// Exercises the three sort-mode entry points through an actual
+		// `Folder`, not just the bare `SortBy` enum. `re_sort()` itself
+		// reorders `self.files` in place, which this snapshot's empty,
+		// constructor-less `Files` can't be populated to observe — these
+		// assertions cover the state each method is responsible for flipping.
+		let mut folder = Folder::new(Path::new("/root"));
+		assert!(folder.sort.by == SortBy::Name);
+
+		folder.cycle_sort();
+		assert!(folder.sort.by == SortBy::Size);
+		folder.cycle_sort();
+		assert!(folder.sort.by == SortBy::MTime);
+		folder.cycle_sort();
+		assert!(folder.sort.by == SortBy::Extension);
+		folder.cycle_sort();
+		assert!(folder.sort.by == SortBy::Name);
+
+		assert!(!folder.sort.reverse);
+		folder.reverse_sort();
+		assert!(folder.sort.reverse);
+		folder.reverse_sort();
+		assert!(!folder.sort.reverse);
+
+		assert!(!folder.sort.dirs_first);
+		folder.toggle_dirs_first();
+		assert!(folder.sort.dirs_first);
+	}
+
+	#[test]
+	fn move_top_and_move_bottom_bound_the_cursor() {
+		let nodes: Vec<TreeNode> =
+			(0..200).map(|i| TreeNode::leaf(PathBuf::from(format!("/root/f{i}")), false)).collect();
+		let mut folder = Folder::new(Path::new("/root"));
+		folder.tree = Some(Tree { nodes });
+
+		assert!(folder.move_bottom());
+		assert_eq!(folder.cursor(), 199);
+
+		assert!(folder.move_top());
+		assert_eq!(folder.cursor(), 0);
+
+		// No-op once already there.
+		assert!(!folder.move_top());
+	}
+
+	#[test]
+	fn page_down_then_page_up_never_overshoots_the_visible_range() {
+		// `Self::limit()` depends on the real terminal size, which a test
+		// environment may report as 0 (no tty); page_step() then moves the
+		// cursor by 0 rows, so this asserts that the cursor stays inside the
+		// 500 rows and that page_up never ends past where page_down left it,
+		// rather than a fixed offset.
+		let nodes: Vec<TreeNode> =
+			(0..500).map(|i| TreeNode::leaf(PathBuf::from(format!("/root/f{i}")), false)).collect();
+		let mut folder = Folder::new(Path::new("/root"));
+		folder.tree = Some(Tree { nodes });
+
+		folder.page_down();
+		let after_down = folder.cursor();
+		assert!(after_down < 500);
+
+		folder.page_up();
+		assert!(folder.cursor() <= after_down);
+	}
+
+	#[test]
+	fn search_next_prev_and_progress_wrap_over_the_visible_range() {
+		let nodes: Vec<TreeNode> =
+			(0..5).map(|i| TreeNode::leaf(PathBuf::from(format!("/root/f{i}")), false)).collect();
+		let mut folder = Folder::new(Path::new("/root"));
+		folder.tree = Some(Tree { nodes });
+		folder.in_search = true;
+
+		assert_eq!(folder.search_progress(), Some((1, 5)));
+
+		folder.search_next();
+		assert_eq!(folder.cursor(), 1);
+		assert_eq!(folder.search_progress(), Some((2, 5)));
+
+		// Wraps from the last row back to the first.
+		folder.move_bottom();
+		folder.search_next();
+		assert_eq!(folder.cursor(), 0);
+
+		// ... and from the first back to the last, going the other way.
+		folder.search_prev();
+		assert_eq!(folder.cursor(), 4);
+	}
+
+	#[test]
+	fn search_progress_is_none_outside_search_mode() {
+		let folder = Folder::new(Path::new("/root"));
+		assert_eq!(folder.search_progress(), None);
+	}
+
+	#[test]
+	fn meta_upto_is_a_no_op_on_an_empty_listing() {
+		// `meta_upto()`'s actual windowed-loading behavior needs `self.files`
+		// populated with real `File` entries (to observe `is_meta_loaded()`/
+		// `load_meta()`/`mark_meta_dirty()` firing correctly across scrolls),
+		// which this snapshot's `core::files`-less `Files` has no constructor
+		// for. This covers the one branch reachable without it: an empty
+		// listing reports nothing changed and touches no metadata state.
+		let mut folder = Folder::new(Path::new("/root"));
+		assert!(!folder.meta_upto(META_LOOKAHEAD));
+		assert!(folder.meta_loaded.is_empty());
+	}
+
+	struct TempDir(PathBuf);
+
+	impl TempDir {
+		fn new(name: &str) -> Self {
+			let dir = std::env::temp_dir().join(format!("yazi-folder-test-{name}-{:x}", std::process::id()));
+			let _ = std::fs::remove_dir_all(&dir);
+			std::fs::create_dir_all(&dir).unwrap();
+			Self(dir)
+		}
+	}
+
+	impl Drop for TempDir {
+		fn drop(&mut self) { let _ = std::fs::remove_dir_all(&self.0); }
+	}
+
+	fn tree_root(path: PathBuf) -> Tree { Tree { nodes: vec![TreeNode::leaf(path, false)] } }
+
+	#[test]
+	fn tree_leaf_keeps_the_selection_state_it_is_given() {
+		// `tree_enter()` builds each depth-0 node via `TreeNode::leaf`, passing
+		// through the backing `File`'s `is_selected` rather than hardcoding
+		// `false`. `Files` can't be populated in this snapshot, so this checks
+		// `TreeNode::leaf` directly: the state passed in is the one the node
+		// carries when read off `tree.nodes`.
+		let selected = TreeNode::leaf(PathBuf::from("/selected"), true);
+		let unselected = TreeNode::leaf(PathBuf::from("/unselected"), false);
+
+		let tree = Tree { nodes: vec![selected, unselected] };
+		assert!(tree.nodes.iter().any(|n| n.is_selected));
+		assert_eq!(
+			tree.nodes.iter().filter(|n| n.is_selected).map(|n| n.path.clone()).collect::<Vec<_>>(),
+			vec![PathBuf::from("/selected")]
+		);
+	}
+
+	#[test]
+	fn hover_repositions_the_cursor_even_when_the_hovered_path_is_unchanged() {
+		// `filter()`, `filter_clear()`, `re_sort()`, and `tree_enter()`/
+		// `tree_leave()` all call `hover()` with the very path `self.hovered`
+		// already holds, right after reshaping the visible listing — only the
+		// path's resolved index changes, never the path itself. This exercises
+		// `hover()` through a real `Folder` (not just the pure `Tree` helpers
+		// above) the same way those callers do, via the tree-backed branch of
+		// `position()`/`sync_hovered()` since constructing `self.files` entries
+		// needs the sibling `core::files` module this snapshot doesn't carry.
+		let mut folder = Folder::new(Path::new("/root"));
+		folder.tree = Some(Tree {
+			nodes: vec![
+				TreeNode::leaf(PathBuf::from("/root/a"), false),
+				TreeNode::leaf(PathBuf::from("/root/b"), false),
+				TreeNode::leaf(PathBuf::from("/root/c"), false),
+			],
+		});
+		folder.cursor = 2;
+
+		// "c" moves from index 2 to index 0, as a reshape like `re_sort()`
+		// would do, with no change to the path being hovered.
+		folder.tree.as_mut().unwrap().nodes.swap(0, 2);
+
+		assert!(folder.hover(Path::new("/root/c")));
+		assert_eq!(folder.cursor, 0);
+	}
+
+	#[test]
+	fn select_matching_prefers_regex_for_non_glob_patterns() {
+		// Neither pattern contains a glob metacharacter, so both should be
+		// tried as regex first rather than silently degraded to a literal
+		// match by `glob_to_regex()`'s escaping.
+		let mut folder = Folder::new(Path::new("/root"));
+		folder.tree = Some(Tree {
+			nodes: vec![
+				TreeNode::leaf(PathBuf::from("/root/foo"), false),
+				TreeNode::leaf(PathBuf::from("/root/bar"), false),
+				TreeNode::leaf(PathBuf::from("/root/baz"), false),
+			],
+		});
+
+		assert!(folder.select_matching("foo|bar", Some(true)));
+		let selected: Vec<_> = folder.tree.as_ref().unwrap().nodes.iter().filter(|n| n.is_selected).collect();
+		assert_eq!(selected.len(), 2);
+		assert!(selected.iter().all(|n| n.path != PathBuf::from("/root/baz")));
+
+		folder.select(None, Some(false));
+		assert!(folder.select_matching("^ba.$", Some(true)));
+		let selected: Vec<_> = folder.tree.as_ref().unwrap().nodes.iter().filter(|n| n.is_selected).collect();
+		assert_eq!(selected.len(), 2);
+		assert!(selected.iter().all(|n| n.path != PathBuf::from("/root/foo")));
+	}
+
+	#[test]
+	fn select_matching_still_honors_glob_syntax() {
+		let mut folder = Folder::new(Path::new("/root"));
+		folder.tree = Some(Tree {
+			nodes: vec![
+				TreeNode::leaf(PathBuf::from("/root/a.rs"), false),
+				TreeNode::leaf(PathBuf::from("/root/a.rsx"), false),
+			],
+		});
+
+		assert!(folder.select_matching("*.rs", Some(true)));
+		let selected: Vec<_> = folder.tree.as_ref().unwrap().nodes.iter().filter(|n| n.is_selected).map(|n| &n.path).collect();
+		assert_eq!(selected, vec![&PathBuf::from("/root/a.rs")]);
+	}
+
+	#[test]
+	fn select_and_invert_selection_toggle_tree_nodes() {
+		let mut folder = Folder::new(Path::new("/root"));
+		folder.tree = Some(Tree {
+			nodes: vec![TreeNode::leaf(PathBuf::from("/root/a"), false), TreeNode::leaf(PathBuf::from("/root/b"), false)],
+		});
+
+		assert!(folder.select(Some(0), Some(true)));
+		assert!(folder.tree.as_ref().unwrap().nodes[0].is_selected);
+		assert!(!folder.tree.as_ref().unwrap().nodes[1].is_selected);
+
+		assert!(folder.invert_selection());
+		assert!(!folder.tree.as_ref().unwrap().nodes[0].is_selected);
+		assert!(folder.tree.as_ref().unwrap().nodes[1].is_selected);
+	}
+
+	#[test]
+	fn tree_expand_splices_children_and_marks_last_branch() {
+		let dir = TempDir::new("expand");
+		std::fs::write(dir.0.join("a.txt"), b"").unwrap();
+		std::fs::write(dir.0.join("b.txt"), b"").unwrap();
+
+		let mut tree = tree_root(dir.0.clone());
+		let dir_to_read = tree.begin_expand(0).unwrap();
+		assert!(tree.nodes[0].expanding);
+
+		tree.apply_expand(&dir_to_read, read_dir_paths(&dir_to_read));
+
+		assert!(tree.nodes[0].expanded);
+		assert!(!tree.nodes[0].expanding);
+		assert_eq!(tree.nodes.len(), 3);
+		assert!(tree.nodes[1].depth == 1 && tree.nodes[2].depth == 1);
+		assert!(*tree.nodes[1].left_branchs.last().unwrap());
+		assert!(!tree.nodes[2].left_branchs.last().unwrap());
+	}
+
+	#[test]
+	fn tree_begin_expand_refuses_a_second_request_while_pending() {
+		let dir = TempDir::new("expand-pending");
+		let mut tree = tree_root(dir.0.clone());
+
+		assert!(tree.begin_expand(0).is_some());
+		assert!(tree.begin_expand(0).is_none());
+	}
+
+	#[test]
+	fn tree_collapse_drains_only_its_own_subtree() {
+		let dir = TempDir::new("collapse");
+		std::fs::write(dir.0.join("a.txt"), b"").unwrap();
+
+		let mut tree = tree_root(dir.0.clone());
+		let dir_to_read = tree.begin_expand(0).unwrap();
+		tree.apply_expand(&dir_to_read, read_dir_paths(&dir_to_read));
+		assert_eq!(tree.nodes.len(), 2);
+
+		tree.collapse(0);
+		assert_eq!(tree.nodes.len(), 1);
+		assert!(!tree.nodes[0].expanded);
+		assert_eq!(tree.nodes[0].unlisted, 0);
+	}
+
+	// Stands in for the items an async `FilesOp::Read` would deliver for
+	// `dir`, since this snapshot doesn't carry the `core::files` module that
+	// would otherwise produce them.
+	fn read_dir_paths(dir: &Path) -> Vec<PathBuf> {
+		std::fs::read_dir(dir).into_iter().flatten().filter_map(|e| e.ok()).map(|e| e.path()).collect()
+	}
+}